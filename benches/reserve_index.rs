@@ -0,0 +1,59 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use reqwest::Url;
+use url_track_cleaner::{ReserveRule, UrlTrackCleaner};
+
+/// Build a large, mostly host-anchored ruleset similar in shape to an adopted
+/// provider ruleset: one rule per synthetic domain, plus a handful of
+/// always-check rules with no extractable host anchor.
+///
+/// `escaped` controls whether the host's dots are written as `\.`, the style
+/// used throughout `src/provider.rs`'s own tests (`example\.com`), rather than
+/// the unescaped `example.com` style. Both must index the same way.
+fn large_rule_set(size: usize, escaped: bool) -> Vec<ReserveRule> {
+    let mut rules = Vec::with_capacity(size);
+    for i in 0..size {
+        let pattern = if escaped {
+            format!(r#"^https?://www\.example{}\.com/.*"#, i)
+        } else {
+            format!(r#"^http(s)?://www.example{}.com/.*"#, i)
+        };
+        rules.push(
+            ReserveRule::new_with_regex(&pattern, vec!["t".to_string()])
+                .expect("failed to create reserve rule"),
+        );
+    }
+    for _ in 0..(size / 100).max(1) {
+        rules.push(
+            ReserveRule::new_with_regex(r#"^http(s)?://.*/special"#, vec!["t".to_string()])
+                .expect("failed to create reserve rule"),
+        );
+    }
+    rules
+}
+
+fn bench_reserve_rule_dispatch(c: &mut Criterion) {
+    let url: Url = "https://www.example999.com/video/BV11111?t=360&track_id=2"
+        .parse()
+        .unwrap();
+
+    let unescaped = UrlTrackCleaner::builder()
+        .reserve_rules(large_rule_set(1_000, false))
+        .build();
+    c.bench_function("dispatch against 1000 unescaped-dot rules, host match near the end", |b| {
+        b.iter(|| unescaped.bench_dispatch(black_box(url.clone())));
+    });
+
+    // The realistic case: rules written with properly escaped regex dots, as
+    // an adopted provider ruleset would be. extract_host_anchor must still
+    // recognize these as host-anchored, or this pays sort/clone overhead on
+    // top of the full linear scan for no speedup at all.
+    let escaped = UrlTrackCleaner::builder()
+        .reserve_rules(large_rule_set(1_000, true))
+        .build();
+    c.bench_function("dispatch against 1000 escaped-dot rules, host match near the end", |b| {
+        b.iter(|| escaped.bench_dispatch(black_box(url.clone())));
+    });
+}
+
+criterion_group!(benches, bench_reserve_rule_dispatch);
+criterion_main!(benches);
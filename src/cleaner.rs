@@ -1,4 +1,6 @@
-use std::str::FromStr;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{format_err, Result};
 use reqwest::{header, redirect, IntoUrl, Url};
@@ -6,9 +8,61 @@ use serde::{Deserialize, Serialize};
 use tokio::net::lookup_host;
 
 use crate::policy::RedirectPolicy;
+use crate::provider::{self, ProviderRules};
+use crate::regex_serde::{deserialize_regex_vec, serialize_regex_vec};
+use crate::reserve_index::ReserveRuleIndex;
 use crate::rules::ReserveRule;
 
 const DEFAULT_USER_AGENT: &'static str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36";
+/// Matches reqwest's own default redirect limit.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Resolve a `Location` header value against the url it was returned for,
+/// per RFC 3986 section 4.2: absolute locations are parsed as-is, protocol-relative
+/// locations (`//host/path`) inherit the base's scheme, and anything else
+/// (an absolute or relative path) is resolved against the base url.
+///
+/// `Url::join` already implements all three cases on its own (an absolute
+/// location in `location` is parsed standalone, ignoring `base`), so there is
+/// nothing left to special-case here.
+fn resolve_location(base: &Url, location: &str) -> Result<Url> {
+    Ok(base.join(location)?)
+}
+
+/// A resolved redirect target cached for `default_cache_ttl`, or until the
+/// origin's own `Cache-Control` says otherwise.
+#[derive(Clone, Debug)]
+struct CacheEntry {
+    resolved: Url,
+    expires_at: Instant,
+}
+
+/// Parse the `Cache-Control` header of the final response and turn it into a ttl
+/// for the cache entry: `None` means "do not cache" (`no-store`/`no-cache`),
+/// `Some(max_age)` overrides `default_ttl` when a `max-age` directive is present.
+fn cache_ttl_from_response(cache_control: Option<&header::HeaderValue>, default_ttl: Duration) -> Option<Duration> {
+    let value = match cache_control.and_then(|v| v.to_str().ok()) {
+        Some(value) => value,
+        None => return Some(default_ttl),
+    };
+    let mut max_age = None;
+    for directive in value.split(',') {
+        let directive = directive.trim();
+        if directive.eq_ignore_ascii_case("no-store") || directive.eq_ignore_ascii_case("no-cache") {
+            return None;
+        }
+        if let Some(seconds) = directive.split_once('=').and_then(|(name, value)| {
+            if name.trim().eq_ignore_ascii_case("max-age") {
+                value.trim().parse::<u64>().ok()
+            } else {
+                None
+            }
+        }) {
+            max_age = Some(seconds);
+        }
+    }
+    Some(max_age.map(Duration::from_secs).unwrap_or(default_ttl))
+}
 
 /// Cleaner for tracking url
 ///
@@ -45,6 +99,13 @@ const DEFAULT_USER_AGENT: &'static str = "Mozilla/5.0 (Windows NT 10.0; Win64; x
 pub struct UrlTrackCleaner {
     follow_redirect: RedirectPolicy,
     reserve_rules: Vec<ReserveRule>,
+    reserve_index: ReserveRuleIndex,
+    provider_rules: ProviderRules,
+    keep_referral_marketing: bool,
+    max_redirects: usize,
+    exclude: Vec<regex::Regex>,
+    default_cache_ttl: Option<Duration>,
+    cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
     user_agent: String,
     client: reqwest::Client,
 }
@@ -65,6 +126,13 @@ impl UrlTrackCleaner {
         Self {
             follow_redirect: Default::default(),
             reserve_rules: Default::default(),
+            reserve_index: Default::default(),
+            provider_rules: Default::default(),
+            keep_referral_marketing: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            exclude: Default::default(),
+            default_cache_ttl: None,
+            cache: Default::default(),
             user_agent: DEFAULT_USER_AGENT.to_string(),
             client,
         }
@@ -78,70 +146,197 @@ impl UrlTrackCleaner {
     }
 
     /// Clean the url by the reserve rules.
+    ///
+    /// If the url matches any pattern in `exclude`, it is returned untouched:
+    /// no HTTP request is made, no redirects are followed, and no query
+    /// parameters are stripped.
+    ///
+    /// Otherwise this follows the full redirect chain (not just a single hop), up to
+    /// `max_redirects` hops, and bails out if the chain revisits a url it has
+    /// already seen. Each resolved hop is still run through the `RedirectPolicy`
+    /// check, so following stops as soon as the chain leaves the allowed domains.
+    ///
+    /// When caching is enabled, a previously resolved redirect target is reused
+    /// instead of re-issuing any requests, until its ttl (the origin's own
+    /// `Cache-Control: max-age`, or the configured default) expires.
     pub async fn do_clean<U>(&self, url: U) -> Result<Url>
     where
         U: IntoUrl,
     {
-        let mut url = url.into_url()?;
-        // test if the redirection check should be skipped
-        if !self.skip_redirect(&url).await {
+        let url = url.into_url()?;
+        if self.exclude.iter().any(|re| re.is_match(url.as_str())) {
+            return Ok(url);
+        }
+        let cache_key = url.to_string();
+        if let Some(resolved) = self.cached(&cache_key) {
+            return Ok(self.do_clean_without_http_check(resolved));
+        }
+
+        let mut url = url;
+        let mut visited = HashSet::new();
+        let mut redirects = 0usize;
+        let mut cache_control = None;
+        loop {
+            // test if the redirection check should be skipped
+            if self.skip_redirect(&url).await {
+                break;
+            }
+            if !visited.insert(url.clone()) {
+                return Err(format_err!("redirect loop detected at {}", url));
+            }
             let rsp = self
                 .client
-                .get(url)
+                .get(url.clone())
                 .header(header::USER_AGENT, &self.user_agent)
                 .send()
                 .await?;
-            // Check if the response is a redirection. If it is, get the location header and parse it as the final url.
-            url = if rsp.status().is_redirection() {
-                let location = rsp
-                    .headers()
-                    .get(header::LOCATION)
-                    .ok_or_else(|| format_err!("no location found"))?;
-                Url::from_str(location.to_str()?)?
-            } else {
-                rsp.url().to_owned()
-            };
+            // Check if the response is a redirection. If it is, get the location header and parse it as the next hop.
+            if !rsp.status().is_redirection() {
+                cache_control = rsp.headers().get(header::CACHE_CONTROL).cloned();
+                url = rsp.url().to_owned();
+                break;
+            }
+            // The initial, non-redirect request above doesn't count against the
+            // limit, so this allows `max_redirects` actual hops, same as
+            // reqwest's own `redirect::Policy::limited`.
+            if redirects >= self.max_redirects {
+                return Err(format_err!(
+                    "exceeded the maximum of {} redirects while resolving {}",
+                    self.max_redirects,
+                    url
+                ));
+            }
+            redirects += 1;
+            let location = rsp
+                .headers()
+                .get(header::LOCATION)
+                .ok_or_else(|| format_err!("no location found"))?;
+            url = resolve_location(&url, location.to_str()?)?;
+        }
+
+        if let Some(default_ttl) = self.default_cache_ttl {
+            if let Some(ttl) = cache_ttl_from_response(cache_control.as_ref(), default_ttl) {
+                self.cache.lock().unwrap().insert(
+                    cache_key,
+                    CacheEntry {
+                        resolved: url.clone(),
+                        expires_at: Instant::now() + ttl,
+                    },
+                );
+            }
         }
+
         Ok(self.do_clean_without_http_check(url))
     }
 
+    /// Look up an unexpired, previously resolved redirect target for `key`.
+    fn cached(&self, key: &str) -> Option<Url> {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.resolved.clone())
+    }
+
+    /// Clear every cached redirect resolution.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Exposed only so benchmarks can exercise reserve-rule dispatch in
+    /// isolation, without the network round trip `do_clean` otherwise
+    /// requires. Not part of the crate's public API.
+    #[doc(hidden)]
+    pub fn bench_dispatch(&self, url: Url) -> Url {
+        self.do_clean_without_http_check(url)
+    }
+
     async fn skip_redirect(&self, url: &Url) -> bool {
         if !self.follow_redirect.test_url(url) {
             return true;
         }
         if let Some(host) = url.host_str() {
-            if let Ok(host) = lookup_host(host).await {
-                return host.count() < 1;
+            // `lookup_host` resolves a `host:port` pair, not a bare host: `Url::host_str`
+            // never includes the port, so it has to be paired back up here or every
+            // lookup fails with "invalid socket address" before DNS is even consulted.
+            let port = url.port_or_known_default().unwrap_or(80);
+            if let Ok(hosts) = lookup_host((host, port)).await {
+                return hosts.count() < 1;
             }
         }
         return true;
     }
 
-    /// Clean the url by the reserve rules without http check.
+    /// Clean the url by the provider rules and the reserve rules, without http check.
     fn do_clean_without_http_check(&self, url: Url) -> Url {
-        // Check if the url matches any reserve rules
-        for rule in &self.reserve_rules {
+        let original_query = url.query().map(str::to_owned);
+        let url = if self.provider_rules.is_empty() {
+            url
+        } else {
+            provider::clean_with_providers(url, &self.provider_rules, self.keep_referral_marketing)
+        };
+        // Whether the provider rules actually had something to say about this
+        // url, as opposed to just passing it through untouched because no
+        // provider's `url_pattern` matched.
+        let provider_rules_applied = url.query().map(str::to_owned) != original_query;
+
+        // Check if the url matches any reserve rules, only running the regex
+        // against the subset of rules the index says could plausibly apply to
+        // this host instead of every rule in the set.
+        let candidates = self.reserve_index.candidates(url.host_str());
+        for &index in &candidates {
+            let rule = &self.reserve_rules[index];
             if rule.url_match.is_match(&url.to_string()) {
-                let mut url = url;
-                let mut query = url.query_pairs().collect::<Vec<_>>();
-                query.retain(|(k, _)| rule.reserve_queries.contains(&k.to_string()));
-                url.set_query(Some(
-                    &query
-                        .iter()
-                        .map(|(k, v)| format!("{}={}", k, v))
-                        .collect::<Vec<_>>()
-                        .join("&"),
-                ));
-                return url;
+                return apply_reserve_rule(url, rule);
+            }
+        }
+
+        // No reserve rule matched this url. If a provider rule already cleaned
+        // it, trust that result instead of wiping it out: the two systems run
+        // alongside each other, so a reserve rule scoped to some other site
+        // must not clobber params a provider rule already vetted on this one.
+        // Only when reserve rules are configured *and* the provider rules had
+        // nothing to say about this url do we fall back to the strict,
+        // deny-by-default behavior of dropping every query parameter.
+        if self.reserve_rules.is_empty() || provider_rules_applied {
+            return url;
+        }
+
+        // The host-anchor index is a best-effort prefilter (see its docs for
+        // the unescaped-dot caveat): before wiping every query parameter,
+        // double check the rules it didn't surface as candidates so an
+        // imprecise anchor never turns into a false "nothing reserves this".
+        for (index, rule) in self.reserve_rules.iter().enumerate() {
+            if candidates.contains(&index) {
+                continue;
+            }
+            if rule.url_match.is_match(&url.to_string()) {
+                return apply_reserve_rule(url, rule);
             }
         }
-        // If the url does not match any reserve rules, remove all queries
+
         let mut url = url;
         url.set_query(None);
         url
     }
 }
 
+/// Narrow `url`'s query down to just the keys `rule.reserve_queries` names.
+fn apply_reserve_rule(url: Url, rule: &ReserveRule) -> Url {
+    let mut url = url;
+    let mut query = url.query_pairs().collect::<Vec<_>>();
+    query.retain(|(k, _)| rule.reserve_queries.contains(&k.to_string()));
+    url.set_query(Some(
+        &query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&"),
+    ));
+    url
+}
+
 /// Builder for `UrlTrackCleaner`
 ///
 /// # Serialization
@@ -152,6 +347,15 @@ impl UrlTrackCleaner {
 pub struct UrlTrackCleanerBuilder {
     follow_redirect: RedirectPolicy,
     reserve_rules: Vec<ReserveRule>,
+    provider_rules: ProviderRules,
+    keep_referral_marketing: bool,
+    max_redirects: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "deserialize_regex_vec", serialize_with = "serialize_regex_vec")
+    )]
+    exclude: Vec<regex::Regex>,
+    default_cache_ttl: Option<Duration>,
     user_agent: Option<String>,
 }
 
@@ -160,6 +364,11 @@ impl Default for UrlTrackCleanerBuilder {
         Self {
             follow_redirect: Default::default(),
             reserve_rules: Default::default(),
+            provider_rules: Default::default(),
+            keep_referral_marketing: false,
+            max_redirects: None,
+            exclude: Default::default(),
+            default_cache_ttl: None,
             user_agent: None,
         }
     }
@@ -183,6 +392,39 @@ impl UrlTrackCleanerBuilder {
         self
     }
 
+    /// Set the ClearURLs-style provider ruleset for the cleaner
+    pub fn provider_rules(mut self, provider_rules: ProviderRules) -> Self {
+        self.provider_rules = provider_rules;
+        self
+    }
+
+    /// Keep referral marketing parameters that provider rules would otherwise remove
+    pub fn keep_referral_marketing(mut self, keep_referral_marketing: bool) -> Self {
+        self.keep_referral_marketing = keep_referral_marketing;
+        self
+    }
+
+    /// Set the maximum number of redirect hops to follow before giving up
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = Some(max_redirects);
+        self
+    }
+
+    /// Set a list of patterns that, when matched, cause `do_clean` to return the
+    /// url untouched instead of cleaning it
+    pub fn exclude(mut self, exclude: Vec<regex::Regex>) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// Enable or disable caching of resolved redirect targets. When enabled,
+    /// `default_ttl` is used unless the origin's own `Cache-Control: max-age`
+    /// says otherwise.
+    pub fn cache(mut self, enabled: bool, default_ttl: Duration) -> Self {
+        self.default_cache_ttl = if enabled { Some(default_ttl) } else { None };
+        self
+    }
+
     /// Set the user agent for the cleaner
     pub fn user_agent(mut self, user_agent: String) -> Self {
         self.user_agent = Some(user_agent);
@@ -193,10 +435,231 @@ impl UrlTrackCleanerBuilder {
     pub fn build(self) -> UrlTrackCleaner {
         let mut cleaner = UrlTrackCleaner::default();
         cleaner.follow_redirect = self.follow_redirect;
+        cleaner.reserve_index = ReserveRuleIndex::build(&self.reserve_rules);
         cleaner.reserve_rules = self.reserve_rules;
+        cleaner.provider_rules = self.provider_rules;
+        cleaner.keep_referral_marketing = self.keep_referral_marketing;
+        cleaner.exclude = self.exclude;
+        cleaner.default_cache_ttl = self.default_cache_ttl;
+        if let Some(max_redirects) = self.max_redirects {
+            cleaner.max_redirects = max_redirects;
+        }
         if let Some(user_agent) = self.user_agent {
             cleaner.user_agent = user_agent;
         }
         cleaner
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::provider::ProviderEntry;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Spawn a throwaway HTTP/1.1 stub server on `127.0.0.1` that answers each
+    /// request by handing its path to `handler` and writing back whatever
+    /// response string it returns. Returns the bound port and a hit counter so
+    /// tests can assert on how many requests were actually sent.
+    async fn spawn_stub_server<F>(handler: F) -> (u16, Arc<AtomicUsize>)
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+        let hits_for_task = hits.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = match listener.accept().await {
+                    Ok(pair) => pair,
+                    Err(_) => break,
+                };
+                hits_for_task.fetch_add(1, Ordering::SeqCst);
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]);
+                    let path = request
+                        .lines()
+                        .next()
+                        .and_then(|line| line.split_whitespace().nth(1))
+                        .unwrap_or("/")
+                        .to_string();
+                    let response = handler(&path);
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+        (port, hits)
+    }
+
+    fn redirect_response(location: &str) -> String {
+        format!("HTTP/1.1 302 Found\r\nLocation: {}\r\nConnection: close\r\nContent-Length: 0\r\n\r\n", location)
+    }
+
+    #[tokio::test]
+    pub async fn test_do_clean_errors_when_redirect_chain_exceeds_max_redirects() {
+        let (port, _hits) = spawn_stub_server(|path| {
+            let next: u32 = path.trim_start_matches("/r/").parse().unwrap_or(0) + 1;
+            redirect_response(&format!("/r/{}", next))
+        })
+        .await;
+        let cleaner = UrlTrackCleaner::builder()
+            .follow_redirect(RedirectPolicy::All)
+            .max_redirects(3)
+            .build();
+        let err = cleaner
+            .do_clean(format!("http://localhost:{}/r/0", port))
+            .await
+            .expect_err("the chain never terminates, so it should hit the hop limit");
+        assert!(err.to_string().contains("exceeded the maximum of 3 redirects"));
+    }
+
+    #[tokio::test]
+    pub async fn test_do_clean_detects_redirect_loop() {
+        let (port, _hits) = spawn_stub_server(|path| match path {
+            "/a" => redirect_response("/b"),
+            _ => redirect_response("/a"),
+        })
+        .await;
+        let cleaner = UrlTrackCleaner::builder().follow_redirect(RedirectPolicy::All).build();
+        let err = cleaner
+            .do_clean(format!("http://localhost:{}/a", port))
+            .await
+            .expect_err("/a and /b redirect to each other forever");
+        assert!(err.to_string().contains("redirect loop detected"));
+    }
+
+    #[tokio::test]
+    pub async fn test_do_clean_stops_following_once_redirect_leaves_allowed_domain() {
+        let (port, hits) = spawn_stub_server(|_path| redirect_response("http://other.example.invalid/done")).await;
+        let cleaner = UrlTrackCleaner::builder()
+            .follow_redirect(RedirectPolicy::Domains(vec!["localhost".to_string()]))
+            .build();
+        let cleaned = cleaner
+            .do_clean(format!("http://localhost:{}/start", port))
+            .await
+            .expect("failed to clean url");
+        assert_eq!(cleaned.as_str(), "http://other.example.invalid/done");
+        // The un-allowed domain must never actually be contacted; only the
+        // initial, allowed hop counts as a real request.
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    fn ok_response() -> String {
+        "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n".to_string()
+    }
+
+    #[tokio::test]
+    pub async fn test_do_clean_cache_hit_skips_the_network_call() {
+        let (port, hits) = spawn_stub_server(|_path| ok_response()).await;
+        let cleaner = UrlTrackCleaner::builder()
+            .follow_redirect(RedirectPolicy::All)
+            .cache(true, Duration::from_secs(60))
+            .build();
+        let url = format!("http://localhost:{}/page?id=1", port);
+        let first = cleaner.do_clean(url.clone()).await.expect("failed to clean url");
+        let second = cleaner.do_clean(url.clone()).await.expect("failed to clean url");
+        assert_eq!(first, second);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    pub fn test_reserve_rule_with_unescaped_dot_matches_host_the_index_skips() {
+        // `www.bilibili.com`'s unescaped dots are regex wildcards, so this
+        // pattern also matches a host like `wwwXbilibiliYcom` even though the
+        // host-anchor index only buckets it under the literal `bilibili.com`.
+        let reserve_rules = vec![ReserveRule::new_with_regex(
+            r#"^http(s)?://www.bilibili.com/.*"#,
+            vec!["t".to_string()],
+        )
+        .expect("failed to create reserve rule")];
+        let cleaner = UrlTrackCleaner::builder().reserve_rules(reserve_rules).build();
+        let cleaned = cleaner.bench_dispatch(Url::parse("https://wwwXbilibiliYcom/video/BV11111?t=360&track_id=2").unwrap());
+        assert_eq!(cleaned.query(), Some("t=360"));
+    }
+
+    #[tokio::test]
+    pub async fn test_exclude_returns_url_untouched() {
+        let cleaner = UrlTrackCleaner::builder()
+            .exclude(vec![regex::Regex::new(r#"^https?://excluded\.example\.com/.*"#).unwrap()])
+            .build();
+        let cleaned = cleaner
+            .do_clean("https://excluded.example.com/page?utm_source=newsletter")
+            .await
+            .expect("failed to clean url");
+        assert_eq!(cleaned.as_str(), "https://excluded.example.com/page?utm_source=newsletter");
+    }
+
+    #[test]
+    pub fn test_reserve_rules_for_another_site_dont_clobber_provider_cleaned_query() {
+        let mut provider_rules = ProviderRules::new();
+        provider_rules.insert(
+            "siteb".to_string(),
+            ProviderEntry {
+                url_pattern: regex::Regex::new(r#"^https?://siteb\.example\.com/.*"#).unwrap(),
+                rules: vec![regex::Regex::new("^utm_.*$").unwrap()],
+                raw_rules: vec![],
+                referral_marketing: vec![],
+                exceptions: vec![],
+                redirections: vec![],
+            },
+        );
+        let reserve_rules = vec![
+            ReserveRule::new_with_regex(r#"^https?://sitea\.example\.com/.*"#, vec!["t".to_string()])
+                .expect("failed to create reserve rule"),
+        ];
+        let cleaner = UrlTrackCleaner::builder()
+            .provider_rules(provider_rules)
+            .reserve_rules(reserve_rules)
+            .build();
+        let cleaned = cleaner.bench_dispatch(Url::parse("https://siteb.example.com/page?utm_source=x&id=1").unwrap());
+        assert_eq!(cleaned.query(), Some("id=1"));
+    }
+
+    #[test]
+    pub fn test_resolve_location() {
+        let base = Url::parse("https://example.com/a/b?x=1").unwrap();
+
+        assert_eq!(
+            resolve_location(&base, "http://other.example/target").unwrap().as_str(),
+            "http://other.example/target"
+        );
+        assert_eq!(
+            resolve_location(&base, "//other.example/target").unwrap().as_str(),
+            "https://other.example/target"
+        );
+        assert_eq!(
+            resolve_location(&base, "/c").unwrap().as_str(),
+            "https://example.com/c"
+        );
+        assert_eq!(
+            resolve_location(&base, "c").unwrap().as_str(),
+            "https://example.com/a/c"
+        );
+    }
+
+    #[test]
+    pub fn test_cache_ttl_from_response() {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::CACHE_CONTROL, "max-age=120".parse().unwrap());
+        assert_eq!(
+            cache_ttl_from_response(headers.get(header::CACHE_CONTROL), Duration::from_secs(5)),
+            Some(Duration::from_secs(120))
+        );
+
+        headers.insert(header::CACHE_CONTROL, "no-store".parse().unwrap());
+        assert_eq!(cache_ttl_from_response(headers.get(header::CACHE_CONTROL), Duration::from_secs(5)), None);
+
+        assert_eq!(
+            cache_ttl_from_response(None, Duration::from_secs(5)),
+            Some(Duration::from_secs(5))
+        );
+    }
+}
@@ -1,7 +1,13 @@
 mod cleaner;
+mod policy;
+mod provider;
+mod regex_serde;
+mod reserve_index;
 mod rules;
 
-pub use cleaner::{RedirectPolicy, UrlTrackCleaner, UrlTrackCleanerBuilder};
+pub use cleaner::{UrlTrackCleaner, UrlTrackCleanerBuilder};
+pub use policy::RedirectPolicy;
+pub use provider::{ProviderEntry, ProviderRules};
 pub use rules::ReserveRule;
 
 #[cfg(test)]
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::regex_serde::{
+    deserialize_regex, deserialize_regex_vec, serialize_regex, serialize_regex_vec,
+};
+
+/// A ClearURLs-style ruleset: provider name -> the rules used to clean urls
+/// belonging to that provider.
+pub type ProviderRules = HashMap<String, ProviderEntry>;
+
+/// A single ClearURLs-style provider entry.
+///
+/// See <https://docs.clearurls.xyz/latest/specs/rules/> for the data format
+/// this is modeled on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderEntry {
+    /// The url must match this pattern for the provider to apply.
+    #[serde(deserialize_with = "deserialize_regex", serialize_with = "serialize_regex")]
+    pub url_pattern: regex::Regex,
+    /// Query keys fully matching any of these are removed.
+    #[serde(default, deserialize_with = "deserialize_regex_vec", serialize_with = "serialize_regex_vec")]
+    pub rules: Vec<regex::Regex>,
+    /// Patterns applied to the whole url string; matched text is excised.
+    #[serde(default, deserialize_with = "deserialize_regex_vec", serialize_with = "serialize_regex_vec")]
+    pub raw_rules: Vec<regex::Regex>,
+    /// Like `rules`, but only removed unless the caller asked to keep referral marketing.
+    #[serde(default, deserialize_with = "deserialize_regex_vec", serialize_with = "serialize_regex_vec")]
+    pub referral_marketing: Vec<regex::Regex>,
+    /// If any of these match the url, the provider is skipped entirely.
+    #[serde(default, deserialize_with = "deserialize_regex_vec", serialize_with = "serialize_regex_vec")]
+    pub exceptions: Vec<regex::Regex>,
+    /// Patterns with one capture group; a match means the captured, percent-decoded
+    /// group is the real target url hidden behind this one.
+    #[serde(default, deserialize_with = "deserialize_regex_vec", serialize_with = "serialize_regex_vec")]
+    pub redirections: Vec<regex::Regex>,
+}
+
+/// Matches `DEFAULT_MAX_REDIRECTS`, the default hop cap `UrlTrackCleaner`
+/// applies to actual HTTP redirect chains; a
+/// provider's `redirections` rule is just as capable of looping, so it gets
+/// the same kind of guard.
+const MAX_REDIRECTIONS: usize = 10;
+
+/// Apply a ClearURLs-style provider ruleset to `url`, stripping tracking
+/// parameters and un-nesting `out?url=...` style redirects without issuing
+/// any HTTP requests.
+pub(crate) fn clean_with_providers(
+    url: Url,
+    provider_rules: &ProviderRules,
+    keep_referral_marketing: bool,
+) -> Url {
+    let mut visited = HashSet::new();
+    clean_with_providers_inner(url, provider_rules, keep_referral_marketing, &mut visited, 0)
+}
+
+/// Provider names, sorted so rule application order (and thus which provider
+/// gets to act on an already-mutated url first) is reproducible across runs
+/// instead of depending on `HashMap`'s randomized iteration order.
+fn sorted_provider_names(provider_rules: &ProviderRules) -> Vec<&String> {
+    let mut names: Vec<&String> = provider_rules.keys().collect();
+    names.sort_unstable();
+    names
+}
+
+fn clean_with_providers_inner(
+    url: Url,
+    provider_rules: &ProviderRules,
+    keep_referral_marketing: bool,
+    visited: &mut HashSet<Url>,
+    depth: usize,
+) -> Url {
+    let mut url = url;
+    if depth >= MAX_REDIRECTIONS || !visited.insert(url.clone()) {
+        // Either a malformed/adversarial `redirections` rule is looping, or we
+        // un-nested `MAX_REDIRECTIONS` hops already; stop digging and return
+        // whatever we've resolved so far rather than recursing forever.
+        return url;
+    }
+    for name in sorted_provider_names(provider_rules) {
+        let entry = &provider_rules[name];
+        let url_str = url.to_string();
+        if !entry.url_pattern.is_match(&url_str) {
+            continue;
+        }
+        if entry.exceptions.iter().any(|re| re.is_match(&url_str)) {
+            continue;
+        }
+        if let Some(redirected) = entry.redirections.iter().find_map(|re| {
+            re.captures(&url_str)
+                .and_then(|captures| captures.get(1))
+                .and_then(|target| Url::from_str(&percent_decode(target.as_str())).ok())
+        }) {
+            // The provider claims this url is just a wrapper around another one;
+            // restart the whole pipeline on the real target instead of continuing
+            // to apply this provider's (now irrelevant) rules.
+            return clean_with_providers_inner(redirected, provider_rules, keep_referral_marketing, visited, depth + 1);
+        }
+
+        url = strip_query_params(url, &entry.rules);
+        if !keep_referral_marketing {
+            url = strip_query_params(url, &entry.referral_marketing);
+        }
+        for re in &entry.raw_rules {
+            let stripped = re.replace_all(url.as_str(), "").into_owned();
+            if let Ok(parsed) = Url::from_str(&stripped) {
+                url = parsed;
+            }
+        }
+    }
+    url
+}
+
+fn strip_query_params(mut url: Url, patterns: &[regex::Regex]) -> Url {
+    if patterns.is_empty() || url.query().is_none() {
+        return url;
+    }
+    // A key is removed only when a pattern matches the whole key, not just part of it.
+    let anchored = patterns
+        .iter()
+        .map(|re| regex::Regex::new(&format!("^(?:{})$", re.as_str())).expect("anchoring a valid regex keeps it valid"))
+        .collect::<Vec<_>>();
+    let remaining = url
+        .query_pairs()
+        .filter(|(k, _)| !anchored.iter().any(|re| re.is_match(k)))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect::<Vec<_>>();
+    if remaining.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(&remaining);
+    }
+    url
+}
+
+/// Percent-decode a single captured value without pulling in a separate
+/// percent-encoding dependency.
+///
+/// This can't be done by handing `raw` to `Url`'s own query parser (e.g. by
+/// embedding it as a query value on a scratch url): that parser stops at the
+/// first literal, un-percent-encoded `&` or `#`, silently truncating captures
+/// that aren't themselves fully percent-encoded.
+fn percent_decode(raw: &str) -> String {
+    fn hex_digit(b: u8) -> Option<u8> {
+        match b {
+            b'0'..=b'9' => Some(b - b'0'),
+            b'a'..=b'f' => Some(b - b'a' + 10),
+            b'A'..=b'F' => Some(b - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+                out.push(hi * 16 + lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| raw.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn entry(
+        url_pattern: &str,
+        rules: &[&str],
+        redirections: &[&str],
+        exceptions: &[&str],
+    ) -> ProviderEntry {
+        ProviderEntry {
+            url_pattern: regex::Regex::new(url_pattern).unwrap(),
+            rules: rules.iter().map(|r| regex::Regex::new(r).unwrap()).collect(),
+            raw_rules: vec![],
+            referral_marketing: vec![],
+            exceptions: exceptions.iter().map(|r| regex::Regex::new(r).unwrap()).collect(),
+            redirections: redirections.iter().map(|r| regex::Regex::new(r).unwrap()).collect(),
+        }
+    }
+
+    #[test]
+    pub fn test_strips_tracking_param() {
+        let mut rules = HashMap::new();
+        rules.insert(
+            "example".to_string(),
+            entry(r#"^https?://example\.com/.*"#, &["^utm_.*$"], &[], &[]),
+        );
+        let url = Url::parse("https://example.com/page?utm_source=newsletter&id=1").unwrap();
+        let cleaned = clean_with_providers(url, &rules, false);
+        assert_eq!(cleaned.query(), Some("id=1"));
+    }
+
+    #[test]
+    pub fn test_exception_skips_provider() {
+        let mut rules = HashMap::new();
+        rules.insert(
+            "example".to_string(),
+            entry(
+                r#"^https?://example\.com/.*"#,
+                &["^utm_.*$"],
+                &[],
+                &["^https?://example\\.com/keep.*"],
+            ),
+        );
+        let url = Url::parse("https://example.com/keep?utm_source=newsletter").unwrap();
+        let cleaned = clean_with_providers(url, &rules, false);
+        assert_eq!(cleaned.query(), Some("utm_source=newsletter"));
+    }
+
+    #[test]
+    pub fn test_redirection_unwraps_target() {
+        let mut rules = HashMap::new();
+        rules.insert(
+            "out".to_string(),
+            entry(r#"^https?://out\.example\.com/.*"#, &[], &["url=(.*)$"], &[]),
+        );
+        let url = Url::parse("https://out.example.com/go?url=https%3A%2F%2Freal.example.com%2Fpage").unwrap();
+        let cleaned = clean_with_providers(url, &rules, false);
+        assert_eq!(cleaned.as_str(), "https://real.example.com/page");
+    }
+
+    #[test]
+    pub fn test_redirection_target_not_fully_percent_encoded() {
+        let mut rules = HashMap::new();
+        rules.insert(
+            "out".to_string(),
+            entry(r#"^https?://out\.example\.com/.*"#, &[], &["url=(.*)$"], &[]),
+        );
+        // The captured target still has its own literal `&` query separator,
+        // as a greedy `url=(.*)$` rule would capture from a real provider.
+        let url = Url::parse("https://out.example.com/go?url=https://real.example.com/page?a=1&b=2").unwrap();
+        let cleaned = clean_with_providers(url, &rules, false);
+        assert_eq!(cleaned.as_str(), "https://real.example.com/page?a=1&b=2");
+    }
+
+    #[test]
+    pub fn test_redirection_cycle_does_not_recurse_forever() {
+        let mut rules = HashMap::new();
+        rules.insert(
+            "out".to_string(),
+            entry(r#"^https?://out\.example\.com/.*"#, &[], &["^(https?://out\\.example\\.com/.*)$"], &[]),
+        );
+        let url = Url::parse("https://out.example.com/go?url=1").unwrap();
+        let cleaned = clean_with_providers(url, &rules, false);
+        assert_eq!(cleaned.as_str(), "https://out.example.com/go?url=1");
+    }
+
+    #[test]
+    pub fn test_providers_applied_in_sorted_order() {
+        let mut rules = HashMap::new();
+        // "zzz" skips any url still carrying `a=1`, so `b` is only removed when
+        // "aaa" has already run and stripped `a`.
+        rules.insert("zzz".to_string(), entry(r#"^https?://example\.com/.*"#, &["^b$"], &[], &["a=1"]));
+        rules.insert("aaa".to_string(), entry(r#"^https?://example\.com/.*"#, &["^a$"], &[], &[]));
+        let url = Url::parse("https://example.com/page?a=1&b=2&c=3").unwrap();
+        let cleaned = clean_with_providers(url, &rules, false);
+        assert_eq!(cleaned.query(), Some("c=3"));
+    }
+}
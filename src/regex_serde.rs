@@ -0,0 +1,35 @@
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serde helpers shared by every rule type that stores a `regex::Regex` (or a
+/// list of them) as a plain string (list of strings) on the wire.
+pub(crate) fn serialize_regex<S>(v: &regex::Regex, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(v.as_str())
+}
+
+pub(crate) fn deserialize_regex<'de, D>(deserializer: D) -> Result<regex::Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    regex::Regex::new(&s).map_err(serde::de::Error::custom)
+}
+
+pub(crate) fn serialize_regex_vec<S>(v: &[regex::Regex], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(v.iter().map(|re| re.as_str()))
+}
+
+pub(crate) fn deserialize_regex_vec<'de, D>(deserializer: D) -> Result<Vec<regex::Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = Vec::<String>::deserialize(deserializer)?;
+    raw.iter()
+        .map(|s| regex::Regex::new(s).map_err(serde::de::Error::custom))
+        .collect()
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::rules::ReserveRule;
+
+/// A prefilter over `ReserveRule`s, bucketing rules by a literal host/domain
+/// anchor extracted from their pattern so `do_clean_without_http_check` only
+/// has to run the full regex against the handful of rules that could plausibly
+/// apply to a url's host, instead of every rule in the set. Rules whose pattern
+/// has no extractable anchor land in `fallback` and are always checked.
+///
+/// This is an approximation, not a guarantee of which rules *could* match a
+/// given host (see the unescaped-dot caveat on `extract_host_anchor`); callers
+/// that need to be sure no rule matches before discarding data should fall
+/// back to checking the rules this index didn't surface as candidates.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReserveRuleIndex {
+    by_host: HashMap<String, Vec<usize>>,
+    fallback: Vec<usize>,
+}
+
+impl ReserveRuleIndex {
+    /// Build an index over `rules`, where each entry is the rule's position in
+    /// the original `Vec<ReserveRule>`.
+    pub(crate) fn build(rules: &[ReserveRule]) -> Self {
+        let mut by_host: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut fallback = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            match extract_host_anchor(rule.url_match.as_str()) {
+                Some(host) => by_host.entry(host).or_default().push(index),
+                None => fallback.push(index),
+            }
+        }
+        Self { by_host, fallback }
+    }
+
+    /// Indices, in original rule order, of the rules that could plausibly match
+    /// a url whose host is `host`.
+    pub(crate) fn candidates(&self, host: Option<&str>) -> Vec<usize> {
+        let mut candidates = self.fallback.clone();
+        if let Some(host) = host {
+            if let Some(indices) = self.by_host.get(strip_www(host)) {
+                candidates.extend(indices.iter().copied());
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
+/// Whether `pattern` contains a `|` outside of any `(...)` group, i.e. an
+/// alternation between two whole branches of the pattern rather than one
+/// scoped to a parenthesized subexpression like `http(s)?`.
+fn has_top_level_alternation(pattern: &str) -> bool {
+    let mut depth = 0u32;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn strip_www(host: &str) -> &str {
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+/// Best-effort extraction of a literal host anchor from a `url_match` pattern,
+/// e.g. `^http(s)?://www.bilibili.com/.*` or the equivalently-escaped
+/// `^http(s)?://www\.bilibili\.com/.*` both yield `Some("bilibili.com")`.
+/// Returns `None` when the pattern has no `scheme://` literal immediately
+/// followed by a plain host (no regex alternation/wildcards), in which case
+/// the rule can only be handled by checking it against every url.
+///
+/// A pattern with a top-level `|` (e.g. `^https?://a\.com/.*|^https?://b\.com/.*`,
+/// an ordinary way to scope one rule to several hosts) bails out to `None` too:
+/// anchoring on just the text after the first `://` would silently drop the
+/// rule from dispatch for every host but the first one it covers.
+///
+/// Known imprecision: an unescaped `.` (as in the first example above) is
+/// technically a regex wildcard, not a literal dot, so this deliberately
+/// treats it as literal anyway to keep the common, unescaped-dot rule style
+/// indexable at all. That means the bucket this rule lands in is an
+/// approximation, not a guarantee — a pattern like `www.bilibili.com` would
+/// also match a host like `wwwXbilibiliYcom`, which this index never
+/// associates it with. `do_clean_without_http_check` compensates for that by
+/// falling back to a full scan of the rules the index skipped before it ever
+/// treats "no indexed candidate matched" as "no rule matches".
+fn extract_host_anchor(pattern: &str) -> Option<String> {
+    if has_top_level_alternation(pattern) {
+        return None;
+    }
+    let after_scheme = pattern.split("://").nth(1)?;
+    let chars: Vec<char> = after_scheme.chars().collect();
+    let mut host = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_ascii_alphanumeric() || c == '-' => {
+                host.push(c);
+                i += 1;
+            }
+            '.' => {
+                host.push('.');
+                i += 1;
+            }
+            '\\' if chars.get(i + 1) == Some(&'.') => {
+                host.push('.');
+                i += 2;
+            }
+            _ => break,
+        }
+    }
+    if !host.contains('.') || host.starts_with(['.', '-']) || host.ends_with(['.', '-']) {
+        return None;
+    }
+    Some(strip_www(&host).to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_extract_host_anchor() {
+        assert_eq!(
+            extract_host_anchor(r#"^http(s)?://www.bilibili.com/.*"#),
+            Some("bilibili.com".to_string())
+        );
+        assert_eq!(extract_host_anchor(r#"^http(s)?://.*"#), None);
+        assert_eq!(extract_host_anchor(r#"^http(s)?://.*\.example\.com/.*"#), None);
+    }
+
+    #[test]
+    pub fn test_extract_host_anchor_escaped_dots() {
+        assert_eq!(
+            extract_host_anchor(r#"^https?://www\.bilibili\.com/.*"#),
+            Some("bilibili.com".to_string())
+        );
+        assert_eq!(
+            extract_host_anchor(r#"^https?://example\.com/.*"#),
+            Some("example.com".to_string())
+        );
+    }
+
+    #[test]
+    pub fn test_candidates_buckets_by_host() {
+        let rules = vec![
+            ReserveRule::new_with_regex(r#"^http(s)?://www.bilibili.com/.*"#, vec!["t".to_string()])
+                .expect("failed to create reserve rule"),
+            ReserveRule::new_with_regex(r#"^http(s)?://.*/special"#, vec![])
+                .expect("failed to create reserve rule"),
+        ];
+        let index = ReserveRuleIndex::build(&rules);
+        assert_eq!(index.candidates(Some("www.bilibili.com")), vec![0, 1]);
+        assert_eq!(index.candidates(Some("acfun.tv")), vec![1]);
+    }
+
+    #[test]
+    pub fn test_extract_host_anchor_bails_on_top_level_alternation() {
+        assert_eq!(
+            extract_host_anchor(r#"^https?://a\.com/.*|^https?://b\.com/.*"#),
+            None
+        );
+        // Alternation confined to a group is still a plain scheme prefix, not
+        // a multi-host pattern, so it's still anchorable.
+        assert_eq!(
+            extract_host_anchor(r#"^http(s)?://example\.com/.*"#),
+            Some("example.com".to_string())
+        );
+    }
+
+    #[test]
+    pub fn test_candidates_includes_rules_for_every_host_in_an_alternation() {
+        let rules = vec![ReserveRule::new_with_regex(
+            r#"^https?://bilibili\.com/.*|^https?://m\.bilibili\.com/.*"#,
+            vec!["t".to_string()],
+        )
+        .expect("failed to create reserve rule")];
+        let index = ReserveRuleIndex::build(&rules);
+        assert_eq!(index.candidates(Some("m.bilibili.com")), vec![0]);
+        assert_eq!(index.candidates(Some("bilibili.com")), vec![0]);
+    }
+
+    #[test]
+    pub fn test_candidates_buckets_by_host_with_escaped_patterns() {
+        let rules = vec![
+            ReserveRule::new_with_regex(r#"^https?://www\.bilibili\.com/.*"#, vec!["t".to_string()])
+                .expect("failed to create reserve rule"),
+            ReserveRule::new_with_regex(r#"^https?://.*/special"#, vec![])
+                .expect("failed to create reserve rule"),
+        ];
+        let index = ReserveRuleIndex::build(&rules);
+        assert_eq!(index.candidates(Some("www.bilibili.com")), vec![0, 1]);
+        assert_eq!(index.candidates(Some("acfun.tv")), vec![1]);
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::regex_serde::{deserialize_regex, serialize_regex};
 
 /// A rule defines how to reserve queries in urls matching the rule.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,21 +28,6 @@ impl ReserveRule {
     }
 }
 
-fn serialize_regex<S>(v: &regex::Regex, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.serialize_str(v.as_str())
-}
-
-fn deserialize_regex<'de, D>(deserializer: D) -> Result<regex::Regex, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    regex::Regex::new(&s).map_err(serde::de::Error::custom)
-}
-
 #[cfg(test)]
 pub mod tests {
     use super::*;